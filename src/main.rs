@@ -14,6 +14,7 @@
 //! - **ThoughtData**: Internal representation of a single thought with metadata
 
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -35,6 +36,12 @@ use serde::{Deserialize, Serialize};
 const JSONRPC_INVALID_PARAMS: i32 = -32602;
 /// Internal JSON-RPC error
 const JSONRPC_INTERNAL_ERROR: i32 = -32603;
+/// Implementation-defined server error: tracked-thought budget exceeded
+/// (reserved server-error range, see the JSON-RPC 2.0 spec)
+const JSONRPC_OVERFLOW: i32 = -32000;
+
+/// Default ceiling on tracked thoughts before an overflow error is returned
+const DEFAULT_LIMIT: u32 = 1024;
 
 /// Deliberate thinking request parameters
 #[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
@@ -97,6 +104,9 @@ pub struct DeliberateThinkingResponse {
     pub branches: Vec<String>,
     #[serde(rename = "thoughtHistoryLength")]
     pub thought_history_length: u32,
+    pub limit: u32,
+    #[serde(rename = "remainingBudget")]
+    pub remaining_budget: u32,
 }
 
 impl DeliberateThinkingResponse {
@@ -105,6 +115,8 @@ impl DeliberateThinkingResponse {
         request: &DeliberateThinkingRequest,
         branches: Vec<String>,
         thought_history_length: u32,
+        limit: u32,
+        remaining_budget: u32,
     ) -> Self {
         Self {
             thought_number: request.thought_number,
@@ -112,21 +124,359 @@ impl DeliberateThinkingResponse {
             next_thought_needed: request.next_thought_needed,
             branches,
             thought_history_length,
+            limit,
+            remaining_budget,
+        }
+    }
+}
+
+/// Request parameters for merging one branch's divergent thoughts into another
+#[derive(Debug, Clone, Deserialize, schemars::JsonSchema)]
+pub struct MergeBranchRequest {
+    #[serde(rename = "fromBranch")]
+    #[schemars(description = "Branch identifier to merge from")]
+    pub from_branch: String,
+    #[serde(rename = "toBranch")]
+    #[schemars(description = "Branch identifier to merge into")]
+    pub to_branch: String,
+}
+
+/// A thought reduced to the fields worth surfacing to callers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThoughtSummary {
+    #[serde(rename = "thoughtNumber")]
+    pub thought_number: u32,
+    pub thought: String,
+}
+
+impl From<&ThoughtData> for ThoughtSummary {
+    fn from(thought: &ThoughtData) -> Self {
+        Self {
+            thought_number: thought.thought_number,
+            thought: thought.thought.clone(),
         }
     }
 }
 
+/// The navigation path between the tips of two branches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreeRoute {
+    #[serde(rename = "commonAncestor")]
+    pub common_ancestor: Option<u32>,
+    pub retracted: Vec<ThoughtSummary>,
+    pub enacted: Vec<ThoughtSummary>,
+}
+
+/// Response for the mergeBranch tool
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeBranchResponse {
+    #[serde(rename = "commonAncestor")]
+    pub common_ancestor: Option<u32>,
+    pub retracted: Vec<ThoughtSummary>,
+    pub enacted: Vec<ThoughtSummary>,
+    pub branches: Vec<String>,
+    #[serde(rename = "toBranchLength")]
+    pub to_branch_length: u32,
+}
+
+/// A JSON-serializable snapshot of the full server state, used to save and
+/// restore sessions across process restarts
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct StateSnapshot {
+    #[serde(rename = "thoughtHistory")]
+    pub thought_history: Vec<ThoughtData>,
+    pub branches: HashMap<String, Vec<ThoughtData>>,
+    #[serde(rename = "currentBranch", skip_serializing_if = "Option::is_none", default)]
+    pub current_branch: Option<String>,
+}
+
+/// Request parameters shared by `saveSession`/`loadSession`: an explicit
+/// file path, or a named session resolved under `--state-dir`
+#[derive(Debug, Clone, Deserialize, schemars::JsonSchema)]
+pub struct SessionFileRequest {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    #[schemars(description = "Explicit file path for the session snapshot")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    #[schemars(description = "Named session, resolved under --state-dir, when path is not given")]
+    pub session: Option<String>,
+}
+
+/// Response for `saveSession`/`loadSession`
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionIoResponse {
+    pub path: String,
+    #[serde(rename = "thoughtHistoryLength")]
+    pub thought_history_length: u32,
+    pub branches: Vec<String>,
+}
+
+/// Saves `state` as a pretty-printed JSON snapshot to `path`
+fn save_snapshot_to_path(state: &DeliberateThinkingState, path: &str) -> std::io::Result<()> {
+    let snapshot = state.to_snapshot();
+    let json = serde_json::to_string_pretty(&snapshot)
+        .map_err(std::io::Error::other)?;
+    std::fs::write(path, json)
+}
+
+/// Loads a JSON snapshot from `path` and restores it into `state`
+fn load_snapshot_from_path(state: &mut DeliberateThinkingState, path: &str) -> std::io::Result<()> {
+    let json = std::fs::read_to_string(path)?;
+    let snapshot: StateSnapshot = serde_json::from_str(&json)
+        .map_err(std::io::Error::other)?;
+    state.restore_snapshot(snapshot);
+    Ok(())
+}
+
+/// A replayable workload: an ordered list of requests to feed through a
+/// fresh server, plus optional assertions checked against the final state
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReplayWorkload {
+    pub requests: Vec<DeliberateThinkingRequest>,
+    #[serde(default)]
+    pub expected: Option<ReplayExpectations>,
+}
+
+/// Assertions checked against the final state after replaying a workload
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReplayExpectations {
+    #[serde(rename = "finalThoughtHistoryLength")]
+    pub final_thought_history_length: Option<u32>,
+    #[serde(rename = "branchCount")]
+    pub branch_count: Option<usize>,
+}
+
+/// Summary of one workload replay run, emitted as machine-readable JSON
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplaySummary {
+    pub file: String,
+    #[serde(rename = "thoughtsProcessed")]
+    pub thoughts_processed: u32,
+    #[serde(rename = "branchesCreated")]
+    pub branches_created: u32,
+    #[serde(rename = "revisionsApplied")]
+    pub revisions_applied: u32,
+    #[serde(rename = "finalThoughtHistoryLength")]
+    pub final_thought_history_length: u32,
+    #[serde(rename = "branchCount")]
+    pub branch_count: usize,
+    #[serde(rename = "wallClockMs")]
+    pub wall_clock_ms: u128,
+    pub mismatches: Vec<String>,
+}
+
+/// Reported when a workload file can't be replayed at all (missing file,
+/// unparseable JSON), emitted in place of a `ReplaySummary` for that file
+/// so one bad file doesn't stop the rest of the run
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayError {
+    pub file: String,
+    pub error: String,
+}
+
+/// Request parameters for exporting the thought graph
+#[derive(Debug, Clone, Deserialize, schemars::JsonSchema)]
+pub struct ExportThoughtGraphRequest {
+    #[schemars(description = "Output format: \"mermaid\", \"dot\", or \"json\"")]
+    pub format: String,
+}
+
+/// A single thought node in the exported thought graph
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphNode {
+    pub id: String,
+    pub label: String,
+}
+
+/// The kind of relationship an edge in the exported thought graph represents
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphEdgeKind {
+    Sequence,
+    Branch,
+    Revision,
+}
+
+/// A single edge in the exported thought graph
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphEdge {
+    pub from: String,
+    pub to: String,
+    pub kind: GraphEdgeKind,
+}
+
+/// The full thought graph: every thought across the main history and all
+/// branches, plus the edges linking them
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct ThoughtGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+impl ThoughtGraph {
+    /// Renders the graph as a Mermaid flowchart, with dashed edges for
+    /// branch points and revisions
+    fn to_mermaid(&self) -> String {
+        let mut out = String::from("flowchart TD\n");
+        for node in &self.nodes {
+            out.push_str(&format!(
+                "    {}[\"{}\"]\n",
+                sanitize_id(&node.id),
+                escape_label(&node.label)
+            ));
+        }
+        for edge in &self.edges {
+            let arrow = match edge.kind {
+                GraphEdgeKind::Sequence => "-->",
+                GraphEdgeKind::Branch => "-. branch .->",
+                GraphEdgeKind::Revision => "-. revises .->",
+            };
+            out.push_str(&format!(
+                "    {} {} {}\n",
+                sanitize_id(&edge.from),
+                arrow,
+                sanitize_id(&edge.to)
+            ));
+        }
+        out
+    }
+
+    /// Renders the graph as Graphviz DOT, with dashed edges for branch
+    /// points and dotted edges for revisions
+    fn to_dot(&self) -> String {
+        let mut out = String::from("digraph ThoughtGraph {\n");
+        for node in &self.nodes {
+            out.push_str(&format!(
+                "    \"{}\" [label=\"{}\"];\n",
+                node.id,
+                escape_label(&node.label)
+            ));
+        }
+        for edge in &self.edges {
+            let style = match edge.kind {
+                GraphEdgeKind::Sequence => "solid",
+                GraphEdgeKind::Branch => "dashed",
+                GraphEdgeKind::Revision => "dotted",
+            };
+            out.push_str(&format!(
+                "    \"{}\" -> \"{}\" [style={}];\n",
+                edge.from, edge.to, style
+            ));
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Sanitizes a node id for embedding in Mermaid source
+fn sanitize_id(id: &str) -> String {
+    id.chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Escapes a label for embedding in a quoted Mermaid/DOT string
+fn escape_label(label: &str) -> String {
+    label.replace('"', "'").replace('\n', " ")
+}
+
+/// Truncates thought text for compact node labels
+fn truncate(text: &str, max_len: usize) -> String {
+    if text.chars().count() <= max_len {
+        text.to_string()
+    } else {
+        let truncated: String = text.chars().take(max_len).collect();
+        format!("{}...", truncated)
+    }
+}
+
+/// Records each thought's revision-parent link (thought number -> the
+/// thought number it revises) into `parents`, skipping in-place overwrites
+fn index_revision_links(thoughts: &[ThoughtData], parents: &mut HashMap<u32, u32>) {
+    for thought in thoughts {
+        if let Some(revises) = thought.revises_thought {
+            if revises != thought.thought_number {
+                parents.insert(thought.thought_number, revises);
+            }
+        }
+    }
+}
+
+/// Length of the shared prefix two thought vectors still agree on
+/// verbatim (same thought number and text at each position)
+fn common_prefix_len(a: &[ThoughtData], b: &[ThoughtData]) -> usize {
+    a.iter()
+        .zip(b.iter())
+        .take_while(|(x, y)| x.thought_number == y.thought_number && x.thought == y.thought)
+        .count()
+}
+
+/// Appends nodes and edges for the thoughts unique to one branch (i.e.
+/// `thoughts[shared_len..]`), anchoring the first new thought's branch edge
+/// into the shared `main-*` node instead of re-emitting the shared prefix as
+/// a disconnected duplicate chain
+fn append_branch_nodes(
+    branch: &str,
+    thoughts: &[ThoughtData],
+    shared_len: usize,
+    nodes: &mut Vec<GraphNode>,
+    edges: &mut Vec<GraphEdge>,
+) {
+    let anchor_number = shared_len.checked_sub(1).and_then(|i| thoughts.get(i)).map(|t| t.thought_number);
+    let mut prev_id: Option<String> = anchor_number.map(|n| format!("main-{}", n));
+
+    for (offset, thought) in thoughts[shared_len..].iter().enumerate() {
+        let id = format!("{}-{}", branch, thought.thought_number);
+        nodes.push(GraphNode {
+            id: id.clone(),
+            label: format!("#{}: {}", thought.thought_number, truncate(&thought.thought, 40)),
+        });
+
+        if let Some(prev) = &prev_id {
+            edges.push(GraphEdge {
+                from: prev.clone(),
+                to: id.clone(),
+                kind: if offset == 0 { GraphEdgeKind::Branch } else { GraphEdgeKind::Sequence },
+            });
+        }
+
+        if let Some(revises) = thought.revises_thought {
+            if revises != thought.thought_number {
+                let from = match anchor_number {
+                    Some(anchor) if revises <= anchor => format!("main-{}", revises),
+                    _ => format!("{}-{}", branch, revises),
+                };
+                edges.push(GraphEdge {
+                    from,
+                    to: id.clone(),
+                    kind: GraphEdgeKind::Revision,
+                });
+            }
+        }
+
+        prev_id = Some(id);
+    }
+}
+
 /// Internal thought data for tracking
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ThoughtData {
     pub thought: String,
+    #[serde(rename = "thoughtNumber")]
     pub thought_number: u32,
+    #[serde(rename = "totalThoughts")]
     pub total_thoughts: u32,
+    #[serde(rename = "nextThoughtNeeded")]
     pub next_thought_needed: bool,
+    #[serde(rename = "isRevision", skip_serializing_if = "Option::is_none", default)]
     pub is_revision: Option<bool>,
+    #[serde(rename = "revisesThought", skip_serializing_if = "Option::is_none", default)]
     pub revises_thought: Option<u32>,
+    #[serde(rename = "branchFromThought", skip_serializing_if = "Option::is_none", default)]
     pub branch_from_thought: Option<u32>,
+    #[serde(rename = "branchId", skip_serializing_if = "Option::is_none", default)]
     pub branch_id: Option<String>,
+    #[serde(rename = "needsMoreThoughts", skip_serializing_if = "Option::is_none", default)]
     pub needs_more_thoughts: Option<bool>,
 }
 
@@ -147,11 +497,32 @@ impl From<DeliberateThinkingRequest> for ThoughtData {
 }
 
 /// Deliberate thinking server state
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct DeliberateThinkingState {
     thought_history: Vec<ThoughtData>,
     branches: HashMap<String, Vec<ThoughtData>>,
     current_branch: Option<String>,
+    /// Ceiling on tracked thoughts before `check_overflow` rejects new ones
+    limit: u32,
+    /// Per-branch adjacency map from a revising thought's number to the
+    /// thought number it revises, used to detect cycles in the revision
+    /// graph. Keyed by branch id (`None` for main) because thought numbers
+    /// are only unique within a single branch: every branch restarts from a
+    /// cloned prefix of main, so sibling branches routinely reuse the same
+    /// numbers for unrelated thoughts.
+    revision_parents: HashMap<Option<String>, HashMap<u32, u32>>,
+}
+
+impl Default for DeliberateThinkingState {
+    fn default() -> Self {
+        Self {
+            thought_history: Vec::new(),
+            branches: HashMap::new(),
+            current_branch: None,
+            limit: DEFAULT_LIMIT,
+            revision_parents: HashMap::new(),
+        }
+    }
 }
 
 impl DeliberateThinkingState {
@@ -190,6 +561,15 @@ impl DeliberateThinkingState {
                 .take_while(|t| t.thought_number <= branch_from)
                 .cloned()
                 .collect();
+
+            // Seed the branch's revision-parent bucket from the inherited
+            // prefix so a cycle spanning the branch point is caught live,
+            // not only after a save/load round-trip through restore_snapshot.
+            index_revision_links(
+                &branch_base,
+                self.revision_parents.entry(Some(branch_id.clone())).or_default(),
+            );
+
             self.branches.insert(branch_id.clone(), branch_base);
         }
 
@@ -201,8 +581,20 @@ impl DeliberateThinkingState {
         self.current_branch = Some(branch_id);
     }
 
-    /// Handles revision of existing thoughts
-    fn handle_revision(&mut self, revises: u32, thought_data: ThoughtData) {
+    /// Handles revision of existing thoughts, rejecting a revision that
+    /// would close a cycle in the revision graph (thought A revises B
+    /// revises C revises back to A)
+    fn handle_revision(&mut self, revises: u32, thought_data: ThoughtData) -> Result<(), McpError> {
+        let new_number = thought_data.thought_number;
+        let branch_key = self.current_branch.clone();
+
+        if new_number != revises && self.would_create_revision_cycle(&branch_key, new_number, revises) {
+            return Err(create_validation_error(&format!(
+                "Revision would create a cycle between thought {} and thought {}",
+                new_number, revises
+            )));
+        }
+
         match &self.current_branch {
             Some(branch_id) => {
                 if let Some(branch) = self.branches.get_mut(branch_id) {
@@ -213,6 +605,87 @@ impl DeliberateThinkingState {
                 Self::revise_or_append(&mut self.thought_history, revises, thought_data);
             }
         }
+
+        if new_number != revises {
+            self.revision_parents
+                .entry(branch_key)
+                .or_default()
+                .insert(new_number, revises);
+        }
+
+        Ok(())
+    }
+
+    /// Walks the revision-parent chain within `branch_key`'s bucket from
+    /// `revises` to check whether it eventually points back to
+    /// `new_number`, which would close a cycle. Branches never share
+    /// revision-parent entries, so an unrelated branch cannot contaminate
+    /// or be contaminated by this check.
+    fn would_create_revision_cycle(
+        &self,
+        branch_key: &Option<String>,
+        new_number: u32,
+        revises: u32,
+    ) -> bool {
+        let Some(parents) = self.revision_parents.get(branch_key) else {
+            return false;
+        };
+
+        let mut current = revises;
+        let mut visited = std::collections::HashSet::new();
+
+        loop {
+            if current == new_number {
+                return true;
+            }
+            if !visited.insert(current) {
+                return false;
+            }
+            match parents.get(&current) {
+                Some(&parent) => current = parent,
+                None => return false,
+            }
+        }
+    }
+
+    /// Length of the thought-history context a request would actually write
+    /// to: the named branch (existing, or about to be created by seeding
+    /// from the `branch_from` prefix of main), or the current context if
+    /// the request isn't a branching request. A request only counts as
+    /// branching when both `branch_id` and `branch_from` are present,
+    /// matching the dispatch condition in `deliberate_thinking` — a
+    /// `branch_id` accompanying e.g. a revision still targets
+    /// `current_branch`.
+    fn target_history_length(&self, branch_id: Option<&str>, branch_from: Option<u32>) -> u32 {
+        // Safe cast: thought history is bounded by practical memory limits
+        // and will never exceed u32::MAX in realistic usage
+        #[allow(clippy::cast_possible_truncation)]
+        match (branch_id, branch_from) {
+            (Some(branch_id), Some(branch_from)) => match self.branches.get(branch_id) {
+                Some(branch) => branch.len() as u32,
+                None => self
+                    .thought_history
+                    .iter()
+                    .take_while(|t| t.thought_number <= branch_from)
+                    .count() as u32,
+            },
+            _ => self.get_history_length(),
+        }
+    }
+
+    /// Returns an overflow error if the context a request would write to —
+    /// the named branch, or the current context — has already reached the
+    /// configured limit
+    fn check_overflow(&self, branch_id: Option<&str>, branch_from: Option<u32>) -> Result<(), McpError> {
+        if self.target_history_length(branch_id, branch_from) >= self.limit {
+            return Err(create_overflow_error(self.limit));
+        }
+        Ok(())
+    }
+
+    /// Remaining thought budget before the limit is reached
+    fn remaining_budget(&self) -> u32 {
+        self.limit.saturating_sub(self.get_history_length())
     }
 
     /// Helper to revise a thought in a list or append if not found
@@ -246,6 +719,136 @@ impl DeliberateThinkingState {
     fn get_branch_names(&self) -> Vec<String> {
         self.branches.keys().cloned().collect()
     }
+
+    /// Builds a JSON-serializable snapshot of the full state
+    fn to_snapshot(&self) -> StateSnapshot {
+        StateSnapshot {
+            thought_history: self.thought_history.clone(),
+            branches: self.branches.clone(),
+            current_branch: self.current_branch.clone(),
+        }
+    }
+
+    /// Replaces the current state with a previously saved snapshot,
+    /// rebuilding the revision-parent adjacency map from the restored
+    /// thoughts so cycle detection keeps working after a reload
+    fn restore_snapshot(&mut self, snapshot: StateSnapshot) {
+        self.thought_history = snapshot.thought_history;
+        self.branches = snapshot.branches;
+        self.current_branch = snapshot.current_branch;
+
+        self.revision_parents.clear();
+        index_revision_links(
+            &self.thought_history,
+            self.revision_parents.entry(None).or_default(),
+        );
+        for (branch_id, thoughts) in &self.branches {
+            index_revision_links(
+                thoughts,
+                self.revision_parents
+                    .entry(Some(branch_id.clone()))
+                    .or_default(),
+            );
+        }
+    }
+
+    /// Builds a graph of every thought across the main history and all
+    /// branches, with edges for linear progression, branch points, and
+    /// revisions
+    fn build_thought_graph(&self) -> ThoughtGraph {
+        let mut graph = ThoughtGraph::default();
+
+        append_branch_nodes("main", &self.thought_history, 0, &mut graph.nodes, &mut graph.edges);
+        for (branch_id, thoughts) in &self.branches {
+            let shared_len = common_prefix_len(&self.thought_history, thoughts);
+            append_branch_nodes(branch_id, thoughts, shared_len, &mut graph.nodes, &mut graph.edges);
+        }
+
+        graph
+    }
+
+    /// Computes the navigation path between the tips of two branches
+    ///
+    /// Walks both branch histories forward while entries agree to find the
+    /// common ancestor (the highest thought both branches still share
+    /// verbatim), then returns the thoughts unique to each branch after that
+    /// point: `retracted` from `from_branch`, `enacted` from `to_branch`.
+    fn tree_route(&self, from_branch: &str, to_branch: &str) -> Result<TreeRoute, McpError> {
+        let from = self.branches.get(from_branch).ok_or_else(|| {
+            create_validation_error(&format!("Unknown branch: {}", from_branch))
+        })?;
+        let to = self
+            .branches
+            .get(to_branch)
+            .ok_or_else(|| create_validation_error(&format!("Unknown branch: {}", to_branch)))?;
+
+        let ancestor_len = common_prefix_len(from, to);
+
+        let common_ancestor = ancestor_len
+            .checked_sub(1)
+            .and_then(|i| from.get(i))
+            .map(|t| t.thought_number);
+
+        let retracted = from[ancestor_len..].iter().map(ThoughtSummary::from).collect();
+        let enacted = to[ancestor_len..].iter().map(ThoughtSummary::from).collect();
+
+        Ok(TreeRoute {
+            common_ancestor,
+            retracted,
+            enacted,
+        })
+    }
+
+    /// Folds `from_branch`'s unique thoughts onto `to_branch`, continuing the
+    /// destination's numbering, and reports what was retracted and enacted
+    /// so the caller can see what was reconciled
+    fn merge_branch(
+        &mut self,
+        from_branch: &str,
+        to_branch: &str,
+    ) -> Result<MergeBranchResponse, McpError> {
+        let route = self.tree_route(from_branch, to_branch)?;
+
+        let next_number = self
+            .branches
+            .get(to_branch)
+            .and_then(|branch| branch.last())
+            .map_or(1, |t| t.thought_number + 1);
+
+        let grafted: Vec<ThoughtData> = route
+            .retracted
+            .iter()
+            .enumerate()
+            .map(|(offset, summary)| ThoughtData {
+                thought: summary.thought.clone(),
+                thought_number: next_number + offset as u32,
+                total_thoughts: next_number + offset as u32,
+                next_thought_needed: false,
+                is_revision: None,
+                revises_thought: None,
+                branch_from_thought: None,
+                branch_id: Some(to_branch.to_string()),
+                needs_more_thoughts: None,
+            })
+            .collect();
+
+        let to_branch_vec = self
+            .branches
+            .get_mut(to_branch)
+            .expect("to_branch existence already checked by tree_route");
+        to_branch_vec.extend(grafted);
+
+        Ok(MergeBranchResponse {
+            common_ancestor: route.common_ancestor,
+            retracted: route.retracted,
+            enacted: route.enacted,
+            branches: self.get_branch_names(),
+            to_branch_length: self
+                .branches
+                .get(to_branch)
+                .map_or(0, |branch| branch.len() as u32),
+        })
+    }
 }
 
 /// Deliberate thinking server implementation
@@ -253,6 +856,9 @@ impl DeliberateThinkingState {
 pub struct DeliberateThinkingServer {
     state: Arc<Mutex<DeliberateThinkingState>>,
     tool_router: ToolRouter<Self>,
+    /// Directory for auto-persisted and named session snapshots, set via
+    /// `--state-dir`
+    state_dir: Option<PathBuf>,
 }
 
 impl DeliberateThinkingServer {
@@ -261,6 +867,17 @@ impl DeliberateThinkingServer {
         Self {
             state: Arc::new(Mutex::new(DeliberateThinkingState::default())),
             tool_router: Self::tool_router(),
+            state_dir: None,
+        }
+    }
+
+    /// Creates a server that auto-persists its state to `state_dir` after
+    /// each thought and resolves named sessions under it
+    #[must_use]
+    pub fn with_state_dir(state_dir: PathBuf) -> Self {
+        Self {
+            state_dir: Some(state_dir),
+            ..Self::new()
         }
     }
 }
@@ -271,6 +888,50 @@ impl Default for DeliberateThinkingServer {
     }
 }
 
+impl DeliberateThinkingServer {
+    /// Snapshot of the current thought history length and branch names, used
+    /// by the replay runner to report final state
+    async fn stats(&self) -> (u32, Vec<String>) {
+        let state = self.state.lock().await;
+        (state.get_history_length(), state.get_branch_names())
+    }
+
+    /// Writes the current state to the auto-persist snapshot under
+    /// `--state-dir`, if one was configured at startup
+    async fn auto_persist(&self) {
+        let Some(dir) = &self.state_dir else {
+            return;
+        };
+        let path = dir.join("session.json");
+        let state = self.state.lock().await;
+        if let Err(err) = save_snapshot_to_path(&state, &path.to_string_lossy()) {
+            log::warn!("Failed to auto-persist session: {}", err);
+        }
+    }
+
+    /// Resolves a `SessionFileRequest` to a concrete file path, preferring
+    /// an explicit path and falling back to a named session under
+    /// `--state-dir`
+    fn resolve_session_path(&self, request: &SessionFileRequest) -> Result<String, McpError> {
+        if let Some(path) = &request.path {
+            return Ok(path.clone());
+        }
+
+        if let Some(session) = &request.session {
+            return match &self.state_dir {
+                Some(dir) => Ok(dir.join(format!("{}.json", session)).to_string_lossy().into_owned()),
+                None => Err(create_validation_error(
+                    "Named sessions require the server to be started with --state-dir",
+                )),
+            };
+        }
+
+        Err(create_validation_error(
+            "Either \"path\" or \"session\" must be provided",
+        ))
+    }
+}
+
 /// Helper function to validate minimum values
 fn validate_min_value(field_name: &str, value: u32, min: u32) -> Result<(), McpError> {
     if value < min {
@@ -291,6 +952,15 @@ fn create_validation_error(message: &str) -> McpError {
     }
 }
 
+/// Helper function to create overflow errors
+fn create_overflow_error(limit: u32) -> McpError {
+    McpError {
+        code: ErrorCode(JSONRPC_OVERFLOW),
+        message: format!("Thought history limit of {} reached", limit).into(),
+        data: None,
+    }
+}
+
 /// Helper function to create serialization errors
 fn create_serialization_error(error: impl std::fmt::Display) -> McpError {
     McpError {
@@ -341,13 +1011,16 @@ Key features:
 
         let mut state = self.state.lock().await;
 
+        // Reject new thoughts once the target context's budget is exhausted
+        state.check_overflow(request.branch_id.as_deref(), request.branch_from_thought)?;
+
         // Process the thought based on its type
         if let (Some(branch_from), Some(branch_id)) = (request.branch_from_thought, &request.branch_id) {
             // Branching case: create or add to a branch
             state.handle_branching(branch_from, branch_id.clone(), thought_data);
         } else if let Some(revises) = request.revises_thought {
             // Revision case: update an existing thought
-            state.handle_revision(revises, thought_data);
+            state.handle_revision(revises, thought_data)?;
         } else {
             // Regular thought case: add to current history
             state.add_thought(thought_data);
@@ -358,8 +1031,13 @@ Key features:
             &request,
             state.get_branch_names(),
             state.get_history_length(),
+            state.limit,
+            state.remaining_budget(),
         );
 
+        // Release the lock before auto-persisting, which re-acquires it
+        drop(state);
+
         // Log the thought for debugging
         log_thought_info(&request);
 
@@ -367,6 +1045,120 @@ Key features:
         let response_json = serde_json::to_value(response)
             .map_err(create_serialization_error)?;
 
+        // Auto-persist after each thought when --state-dir is configured
+        self.auto_persist().await;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response_json.to_string(),
+        )]))
+    }
+
+    /// Reconciles two divergent branches of thought
+    #[tool(
+        name = "mergeBranch",
+        description = "Reconciles two divergent branches of thought. Finds the common ancestor thought between from_branch and to_branch, folds from_branch's unique thoughts onto the end of to_branch (renumbered to continue its sequence), and reports which thoughts were retracted (abandoned from from_branch) and enacted (already present on to_branch) past that point."
+    )]
+    pub async fn merge_branch(
+        &self,
+        Parameters(request): Parameters<MergeBranchRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let mut state = self.state.lock().await;
+
+        let response = state.merge_branch(&request.from_branch, &request.to_branch)?;
+
+        let response_json = serde_json::to_value(response).map_err(create_serialization_error)?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response_json.to_string(),
+        )]))
+    }
+
+    /// Exports the full thought graph in the requested format
+    #[tool(
+        name = "exportThoughtGraph",
+        description = "Exports the full thought graph (main history, all branches, branch points, and revisions) in the requested format: \"mermaid\", \"dot\", or \"json\"."
+    )]
+    pub async fn export_thought_graph(
+        &self,
+        Parameters(request): Parameters<ExportThoughtGraphRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let state = self.state.lock().await;
+        let graph = state.build_thought_graph();
+
+        let output = match request.format.as_str() {
+            "mermaid" => graph.to_mermaid(),
+            "dot" => graph.to_dot(),
+            "json" => serde_json::to_value(&graph)
+                .map_err(create_serialization_error)?
+                .to_string(),
+            other => {
+                return Err(create_validation_error(&format!(
+                    "Unknown format: {} (expected \"mermaid\", \"dot\", or \"json\")",
+                    other
+                )))
+            }
+        };
+
+        Ok(CallToolResult::success(vec![Content::text(output)]))
+    }
+
+    /// Saves the current session to a JSON snapshot file
+    #[tool(
+        name = "saveSession",
+        description = "Saves the current session (thought history, branches, and current branch) as a JSON snapshot, either to an explicit \"path\" or a named \"session\" resolved under --state-dir."
+    )]
+    pub async fn save_session(
+        &self,
+        Parameters(request): Parameters<SessionFileRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let path = self.resolve_session_path(&request)?;
+        let state = self.state.lock().await;
+
+        save_snapshot_to_path(&state, &path).map_err(|e| {
+            McpError {
+                code: ErrorCode(JSONRPC_INTERNAL_ERROR),
+                message: format!("Failed to save session to {}: {}", path, e).into(),
+                data: None,
+            }
+        })?;
+
+        let response = SessionIoResponse {
+            path,
+            thought_history_length: state.get_history_length(),
+            branches: state.get_branch_names(),
+        };
+        let response_json = serde_json::to_value(response).map_err(create_serialization_error)?;
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response_json.to_string(),
+        )]))
+    }
+
+    /// Loads a session from a JSON snapshot file, replacing current state
+    #[tool(
+        name = "loadSession",
+        description = "Loads a session snapshot, replacing the current thought history and branches, from an explicit \"path\" or a named \"session\" resolved under --state-dir."
+    )]
+    pub async fn load_session(
+        &self,
+        Parameters(request): Parameters<SessionFileRequest>,
+    ) -> Result<CallToolResult, McpError> {
+        let path = self.resolve_session_path(&request)?;
+        let mut state = self.state.lock().await;
+
+        load_snapshot_from_path(&mut state, &path).map_err(|e| McpError {
+            code: ErrorCode(JSONRPC_INTERNAL_ERROR),
+            message: format!("Failed to load session from {}: {}", path, e).into(),
+            data: None,
+        })?;
+
+        let response = SessionIoResponse {
+            path,
+            thought_history_length: state.get_history_length(),
+            branches: state.get_branch_names(),
+        };
+        let response_json = serde_json::to_value(response).map_err(create_serialization_error)?;
+
         Ok(CallToolResult::success(vec![Content::text(
             response_json.to_string(),
         )]))
@@ -414,11 +1206,157 @@ impl ServerHandler for DeliberateThinkingServer {
     }
 }
 
+/// Feeds one workload file through a fresh server and reports timing,
+/// final state, and any assertion mismatches
+async fn run_replay(path: &str) -> Result<ReplaySummary, Box<dyn std::error::Error>> {
+    let contents = std::fs::read_to_string(path)?;
+    let workload: ReplayWorkload = serde_json::from_str(&contents)?;
+
+    let server = DeliberateThinkingServer::new();
+    let mut branches_seen = std::collections::HashSet::new();
+    let mut revisions_applied = 0u32;
+    let mut thoughts_processed = 0u32;
+
+    let mut mismatches = Vec::new();
+
+    let start = std::time::Instant::now();
+    for (index, request) in workload.requests.iter().enumerate() {
+        // A single rejected request (overflow, revision cycle, validation)
+        // shouldn't abort the rest of the workload - record it and move on.
+        match server.deliberate_thinking(Parameters(request.clone())).await {
+            Ok(_) => {
+                thoughts_processed += 1;
+                // Mirror deliberate_thinking's dispatch: branching wins over revision
+                if let (Some(_), Some(branch_id)) = (request.branch_from_thought, &request.branch_id) {
+                    branches_seen.insert(branch_id.clone());
+                } else if request.revises_thought.is_some() {
+                    revisions_applied += 1;
+                }
+            }
+            Err(err) => mismatches.push(format!("request {}: {:?}", index + 1, err)),
+        }
+    }
+    let wall_clock_ms = start.elapsed().as_millis();
+
+    let (final_thought_history_length, branch_names) = server.stats().await;
+    let branch_count = branch_names.len();
+
+    if let Some(expected) = &workload.expected {
+        if let Some(expected_len) = expected.final_thought_history_length {
+            if expected_len != final_thought_history_length {
+                mismatches.push(format!(
+                    "finalThoughtHistoryLength: expected {}, got {}",
+                    expected_len, final_thought_history_length
+                ));
+            }
+        }
+        if let Some(expected_count) = expected.branch_count {
+            if expected_count != branch_count {
+                mismatches.push(format!(
+                    "branchCount: expected {}, got {}",
+                    expected_count, branch_count
+                ));
+            }
+        }
+    }
+
+    Ok(ReplaySummary {
+        file: path.to_string(),
+        thoughts_processed,
+        branches_created: branches_seen.len() as u32,
+        revisions_applied,
+        final_thought_history_length,
+        branch_count,
+        wall_clock_ms,
+        mismatches,
+    })
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
 
-    let server = DeliberateThinkingServer::new();
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 1 && args[1] == "replay" {
+        for path in &args[2..] {
+            // A missing file or unparseable workload shouldn't stop the
+            // remaining files from being replayed - report it and continue.
+            match run_replay(path).await {
+                Ok(summary) => println!("{}", serde_json::to_string(&summary)?),
+                Err(err) => println!(
+                    "{}",
+                    serde_json::to_string(&ReplayError {
+                        file: path.clone(),
+                        error: err.to_string(),
+                    })?
+                ),
+            }
+        }
+        return Ok(());
+    }
+
+    // Parse an optional `--state-dir <path>` flag for session persistence
+    let mut state_dir: Option<PathBuf> = None;
+    let mut i = 1;
+    while i < args.len() {
+        if args[i] == "--state-dir" {
+            if let Some(value) = args.get(i + 1) {
+                state_dir = Some(PathBuf::from(value));
+                i += 1;
+            }
+        }
+        i += 1;
+    }
+
+    let server = match &state_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)?;
+            let server = DeliberateThinkingServer::with_state_dir(dir.clone());
+
+            let snapshot_path = dir.join("session.json");
+            if snapshot_path.exists() {
+                let mut state = server.state.lock().await;
+                if let Err(err) = load_snapshot_from_path(&mut state, &snapshot_path.to_string_lossy()) {
+                    log::warn!(
+                        "Failed to reload session from {}: {}",
+                        snapshot_path.display(),
+                        err
+                    );
+                }
+            }
+
+            server
+        }
+        None => DeliberateThinkingServer::new(),
+    };
+
+    // Flush the current state to disk on Ctrl-C / SIGTERM so no thoughts
+    // are lost on shutdown
+    if state_dir.is_some() {
+        let shutdown_server = server.clone();
+        tokio::spawn(async move {
+            let ctrl_c = tokio::signal::ctrl_c();
+
+            #[cfg(unix)]
+            {
+                let mut terminate =
+                    tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+                        .expect("failed to install SIGTERM handler");
+                tokio::select! {
+                    _ = ctrl_c => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            #[cfg(not(unix))]
+            {
+                let _ = ctrl_c.await;
+            }
+
+            log::info!("Shutdown signal received, flushing session state");
+            shutdown_server.auto_persist().await;
+            std::process::exit(0);
+        });
+    }
 
     log::info!("Starting Deliberate Thinking MCP Server");
 
@@ -530,6 +1468,358 @@ mod tests {
         assert_eq!(state.current_branch, Some("alt-path".to_string()));
     }
 
+    fn thought(thought_number: u32, text: &str) -> ThoughtData {
+        ThoughtData {
+            thought: text.to_string(),
+            thought_number,
+            total_thoughts: thought_number,
+            next_thought_needed: true,
+            is_revision: None,
+            revises_thought: None,
+            branch_from_thought: None,
+            branch_id: None,
+            needs_more_thoughts: None,
+        }
+    }
+
+    #[test]
+    fn test_tree_route_finds_common_ancestor_and_divergent_tails() {
+        let mut state = DeliberateThinkingState::default();
+        state.add_thought(thought(1, "shared"));
+
+        state.handle_branching(1, "branch-a".to_string(), thought(2, "a-only"));
+        state.current_branch = None;
+        state.handle_branching(1, "branch-b".to_string(), thought(2, "b-only"));
+
+        let route = state.tree_route("branch-a", "branch-b").unwrap();
+
+        assert_eq!(route.common_ancestor, Some(1));
+        assert_eq!(route.retracted.len(), 1);
+        assert_eq!(route.retracted[0].thought, "a-only");
+        assert_eq!(route.enacted.len(), 1);
+        assert_eq!(route.enacted[0].thought, "b-only");
+    }
+
+    #[test]
+    fn test_tree_route_rejects_unknown_branch() {
+        let state = DeliberateThinkingState::default();
+        let result = state.tree_route("nope", "also-nope");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_merge_branch_grafts_retracted_thoughts_onto_destination() {
+        let mut state = DeliberateThinkingState::default();
+        state.add_thought(thought(1, "shared"));
+
+        state.handle_branching(1, "branch-a".to_string(), thought(2, "a-only"));
+        state.current_branch = None;
+        state.handle_branching(1, "branch-b".to_string(), thought(2, "b-only"));
+
+        let response = state.merge_branch("branch-a", "branch-b").unwrap();
+
+        assert_eq!(response.to_branch_length, 3);
+        assert_eq!(response.retracted[0].thought, "a-only");
+        assert_eq!(
+            state.branches.get("branch-b").unwrap().last().unwrap().thought,
+            "a-only"
+        );
+    }
+
+    #[test]
+    fn test_check_overflow_rejects_once_limit_reached() {
+        let mut state = DeliberateThinkingState {
+            limit: 1,
+            ..DeliberateThinkingState::default()
+        };
+        state.add_thought(thought(1, "first"));
+
+        assert!(state.check_overflow(None, None).is_err());
+        assert_eq!(state.remaining_budget(), 0);
+    }
+
+    #[test]
+    fn test_check_overflow_targets_the_branch_the_request_will_write_to() {
+        let mut state = DeliberateThinkingState {
+            limit: 4,
+            ..DeliberateThinkingState::default()
+        };
+        state.add_thought(thought(1, "a"));
+        state.add_thought(thought(2, "b"));
+        state.add_thought(thought(3, "c"));
+        state.add_thought(thought(4, "d"));
+
+        // Switch the active context to a small, unrelated branch.
+        state.handle_branching(1, "tiny".to_string(), thought(2, "tiny-2"));
+
+        // A brand-new branch seeded from all four thoughts of main would
+        // already be at the limit, even though the currently active
+        // branch ("tiny") has plenty of room.
+        assert!(state.check_overflow(Some("new-branch"), Some(4)).is_err());
+
+        // The currently active branch is nowhere near the limit, and a
+        // request that doesn't target a branch must still check it instead
+        // of an unrelated context.
+        assert!(state.check_overflow(None, None).is_ok());
+    }
+
+    #[test]
+    fn test_check_overflow_ignores_branch_id_without_branch_from() {
+        let mut state = DeliberateThinkingState {
+            limit: 2,
+            ..DeliberateThinkingState::default()
+        };
+        state.add_thought(thought(1, "a"));
+        state.add_thought(thought(2, "b"));
+        state.handle_branching(1, "tiny".to_string(), thought(2, "tiny-2"));
+        // Current context ("tiny") now has 2 thoughts and is at the limit,
+        // but "other-branch" doesn't exist and has nothing in it.
+
+        // A request carrying branch_id but no branch_from (e.g. a revision
+        // tagged with branchId) isn't a branching request per
+        // deliberate_thinking's dispatch, so it must still be checked
+        // against the current context, not treated as targeting
+        // "other-branch".
+        assert!(state.check_overflow(Some("other-branch"), None).is_err());
+    }
+
+    #[test]
+    fn test_handle_revision_rejects_cycle() {
+        let mut state = DeliberateThinkingState::default();
+        state.add_thought(thought(1, "a"));
+
+        // Thought 2 revises thought 1
+        state.handle_revision(1, thought(2, "b")).unwrap();
+        // Thought 3 revises thought 2
+        state.handle_revision(2, thought(3, "c")).unwrap();
+        // Thought 1 revising thought 3 would close the cycle 1 -> 3 -> 2 -> 1
+        let result = state.handle_revision(3, thought(1, "d"));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_handle_revision_allows_acyclic_chain() {
+        let mut state = DeliberateThinkingState::default();
+        state.add_thought(thought(1, "a"));
+
+        assert!(state.handle_revision(1, thought(2, "b")).is_ok());
+        assert!(state.handle_revision(2, thought(3, "c")).is_ok());
+    }
+
+    #[test]
+    fn test_sibling_branches_do_not_contaminate_revision_cycle_detection() {
+        let mut state = DeliberateThinkingState::default();
+        state.add_thought(thought(1, "root"));
+
+        // Branch A continues its own numbering and records `4 -> 1`.
+        state.handle_branching(1, "branch-a".to_string(), thought(2, "a-2"));
+        state.handle_revision(1, thought(4, "a-revises-1")).unwrap();
+
+        // Branch B has no thought of its own numbered 4; an unrelated new
+        // thought numbered 1 that revises thought 4 must not be rejected
+        // just because branch A's entry for key 4 is sitting in the map.
+        state.current_branch = None;
+        state.handle_branching(1, "branch-b".to_string(), thought(2, "b-2"));
+        let result = state.handle_revision(4, thought(1, "b-revises-4"));
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_handle_revision_rejects_cycle_spanning_branch_point() {
+        let mut state = DeliberateThinkingState::default();
+        state.add_thought(thought(1, "a"));
+
+        // Thought 2 revises thought 1 on main: revision_parents[None] = {2: 1}
+        let mut revision = thought(2, "b");
+        revision.revises_thought = Some(1);
+        state.handle_revision(1, revision).unwrap();
+
+        // Branch off at thought 2; the branch inherits thought 2 (which
+        // replaced thought 1 in place), including main's 2 -> 1 revision
+        // link, which must be seeded into the branch's own bucket up front.
+        state.handle_branching(2, "alt".to_string(), thought(3, "alt-3"));
+
+        // Within "alt", thought 1 revising thought 2 would close the cycle
+        // 1 -> 2 -> 1 that spans the branch point, and must be rejected
+        // live, not only after a save/load round-trip.
+        let result = state.handle_revision(2, thought(1, "alt-revises-2"));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_build_thought_graph_includes_branch_edge() {
+        let mut state = DeliberateThinkingState::default();
+        state.add_thought(thought(1, "root"));
+        let mut branch_thought = thought(2, "branch thought");
+        branch_thought.branch_from_thought = Some(1);
+        state.handle_branching(1, "alt-path".to_string(), branch_thought);
+
+        let graph = state.build_thought_graph();
+
+        assert!(graph.nodes.iter().any(|n| n.id == "main-1"));
+        assert!(graph.nodes.iter().any(|n| n.id == "alt-path-2"));
+        assert!(graph
+            .edges
+            .iter()
+            .any(|e| e.from == "main-1" && e.to == "alt-path-2" && e.kind == GraphEdgeKind::Branch));
+    }
+
+    #[test]
+    fn test_build_thought_graph_does_not_duplicate_shared_prefix() {
+        let mut state = DeliberateThinkingState::default();
+        state.add_thought(thought(1, "root"));
+        state.add_thought(thought(2, "second"));
+        let mut branch_thought = thought(3, "branch thought");
+        branch_thought.branch_from_thought = Some(2);
+        state.handle_branching(2, "alt-path".to_string(), branch_thought);
+
+        let graph = state.build_thought_graph();
+
+        // The shared prefix (thoughts 1 and 2) must appear only once, as
+        // main-1/main-2, not duplicated as alt-path-1/alt-path-2.
+        assert!(!graph.nodes.iter().any(|n| n.id == "alt-path-1"));
+        assert!(!graph.nodes.iter().any(|n| n.id == "alt-path-2"));
+        assert_eq!(graph.nodes.iter().filter(|n| n.id == "main-1").count(), 1);
+        assert_eq!(graph.nodes.iter().filter(|n| n.id == "main-2").count(), 1);
+
+        // Only the genuinely new thought gets an id on the branch, anchored
+        // back into the shared main-2 node.
+        assert!(graph.nodes.iter().any(|n| n.id == "alt-path-3"));
+        assert!(graph
+            .edges
+            .iter()
+            .any(|e| e.from == "main-2" && e.to == "alt-path-3" && e.kind == GraphEdgeKind::Branch));
+    }
+
+    #[test]
+    fn test_thought_graph_mermaid_and_dot_render_nodes() {
+        let mut state = DeliberateThinkingState::default();
+        state.add_thought(thought(1, "root"));
+        let graph = state.build_thought_graph();
+
+        let mermaid = graph.to_mermaid();
+        assert!(mermaid.starts_with("flowchart TD"));
+        assert!(mermaid.contains("main_1"));
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph ThoughtGraph"));
+        assert!(dot.contains("\"main-1\""));
+    }
+
+    #[test]
+    fn test_snapshot_round_trips_history_and_branches() {
+        let mut state = DeliberateThinkingState::default();
+        state.add_thought(thought(1, "root"));
+        state.handle_branching(1, "alt-path".to_string(), thought(2, "branch thought"));
+        let mut revision = thought(3, "revised");
+        revision.revises_thought = Some(2);
+        state.handle_revision(2, revision).unwrap();
+
+        let snapshot = state.to_snapshot();
+
+        let mut restored = DeliberateThinkingState::default();
+        restored.restore_snapshot(snapshot);
+
+        assert_eq!(restored.thought_history.len(), state.thought_history.len());
+        assert_eq!(restored.branches.len(), state.branches.len());
+        assert_eq!(restored.current_branch, state.current_branch);
+        // Cycle detection must still work against the rebuilt revision graph
+        assert!(restored.would_create_revision_cycle(&Some("alt-path".to_string()), 2, 3));
+    }
+
+    #[test]
+    fn test_save_and_load_snapshot_round_trip() {
+        let mut state = DeliberateThinkingState::default();
+        state.add_thought(thought(1, "root"));
+
+        let path = std::env::temp_dir().join("deliberate_thinking_snapshot_test.json");
+        save_snapshot_to_path(&state, path.to_str().unwrap()).unwrap();
+
+        let mut restored = DeliberateThinkingState::default();
+        load_snapshot_from_path(&mut restored, path.to_str().unwrap()).unwrap();
+
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(restored.get_history_length(), 1);
+        assert_eq!(restored.get_current_history()[0].thought, "root");
+    }
+
+    #[tokio::test]
+    async fn test_run_replay_reports_stats_and_mismatches() {
+        let workload = serde_json::json!({
+            "requests": [
+                {
+                    "thought": "first",
+                    "nextThoughtNeeded": true,
+                    "thoughtNumber": 1,
+                    "totalThoughts": 1
+                }
+            ],
+            "expected": {
+                "finalThoughtHistoryLength": 2
+            }
+        });
+
+        let path = std::env::temp_dir().join("deliberate_thinking_replay_test.json");
+        std::fs::write(&path, workload.to_string()).unwrap();
+
+        let summary = run_replay(path.to_str().unwrap()).await.unwrap();
+
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(summary.thoughts_processed, 1);
+        assert_eq!(summary.final_thought_history_length, 1);
+        assert_eq!(summary.mismatches.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_run_replay_records_rejected_request_instead_of_aborting() {
+        let workload = serde_json::json!({
+            "requests": [
+                {
+                    "thought": "first",
+                    "nextThoughtNeeded": true,
+                    "thoughtNumber": 1,
+                    "totalThoughts": 1
+                },
+                {
+                    "thought": "bad thought number",
+                    "nextThoughtNeeded": true,
+                    "thoughtNumber": 0,
+                    "totalThoughts": 1
+                },
+                {
+                    "thought": "third",
+                    "nextThoughtNeeded": true,
+                    "thoughtNumber": 2,
+                    "totalThoughts": 2
+                }
+            ]
+        });
+
+        let path = std::env::temp_dir().join("deliberate_thinking_replay_rejected_test.json");
+        std::fs::write(&path, workload.to_string()).unwrap();
+
+        let summary = run_replay(path.to_str().unwrap()).await.unwrap();
+
+        std::fs::remove_file(&path).ok();
+
+        // The invalid second request is recorded as a mismatch, but the
+        // valid first and third requests still went through.
+        assert_eq!(summary.thoughts_processed, 2);
+        assert_eq!(summary.final_thought_history_length, 2);
+        assert_eq!(summary.mismatches.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_run_replay_returns_error_for_missing_file() {
+        let result = run_replay("/nonexistent/deliberate_thinking_replay.json").await;
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_validate_accepts_valid_request() {
         let request = DeliberateThinkingRequest {